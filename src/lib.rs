@@ -25,6 +25,15 @@
 //! - use `ToSocketAddrsWithDefaultPortAsync` instead of `async_std::net::ToSocketAddrs`,
 //! - use `ToSocketAddrsWithDefaultPortTokio` instead of `tokio::net::ToSocketAddrs`.
 //!
+//! To resolve an address right away, use `resolve_ordered(...)` (or `resolve_ordered_async(...)`,
+//! `resolve_ordered_tokio(...)`): it applies the default port, removes duplicate addresses and
+//! orders them by family according to the given `Preference`. Without an async runtime,
+//! `resolve_in_background(...)` does the same on a worker thread and returns a handle to poll.
+//!
+//! To validate user input early, parse it with `AddrParser` into a `HostAndPort`: malformed input
+//! (for example, a non-numeric port) is reported as a `ParseError` instead of failing later during
+//! resolution.
+//!
 //! ## Features
 //! 
 //! - `sync` *(enabled by default)*
@@ -38,8 +47,56 @@
 //! - `tokio`
 //! 
 //!     Enables `ToSocketAddrsWithDefaultPortTokio`.
+//!
+//! - `full`
+//!
+//!     Enables all of the above.
+//!
+//! - `minimal`
+//!
+//!     Enables `sync` only, without any async runtime dependencies.
+//!
+//! - `test_util`
+//!
+//!     Enables the `test_util` module with helpers for tests of address handling.
+//!
+//! With `default-features = false` and none of `sync`, `async` and `tokio`, only the parser is built
+//! (`AddrParser`, `HostAndPort` and the formatting helpers), without the traits and without any
+//! runtime dependencies, which suits build-time tools validating endpoints in configs.
 //! 
 //! 
+//! ## Custom runtimes
+//!
+//! `.with_default_port(...)` itself never touches the network: it only builds a value of the `Inner`
+//! type, and the actual resolution happens when that value is passed to `std::net`, `async-std` or
+//! `tokio`. The `Inner` types of the sync trait all implement `std::net::ToSocketAddrs` (`String`,
+//! `SocketAddr`, a tuple, a slice...), so a crate with its own runtime does not need a separate trait
+//! variant: take the sync trait as the bound and resolve the result off the executor threads.
+//!
+//! ```rust
+//! use std::{io, net::{SocketAddr, ToSocketAddrs}, thread::{self, JoinHandle}};
+//! use to_socket_addrs::ToSocketAddrsWithDefaultPort;
+//!
+//! // Runs the blocking lookup on its own thread; an in-house executor would use its blocking pool.
+//! fn resolve<A>(addr: A) -> JoinHandle<io::Result<Vec<SocketAddr>>>
+//! where
+//!     A: ToSocketAddrsWithDefaultPort,
+//!     A::Inner: Send + 'static,
+//! {
+//!     let inner = addr.with_default_port(80);
+//!     thread::spawn(move || Ok(inner.to_socket_addrs()?.collect()))
+//! }
+//!
+//! let addrs = resolve("127.0.0.1").join().unwrap().unwrap();
+//! assert_eq!(addrs, ["127.0.0.1:80".parse::<SocketAddr>().unwrap()]);
+//! ```
+//!
+//!
+//! The variants for `async-std` and `tokio` are generated from the same source with
+//! [maybe-async-cfg](https://crates.io/crates/maybe-async-cfg); they only differ in the
+//! `ToSocketAddrs` trait the `Inner` type is required to implement.
+//!
+//! 
 //! ## Explanation
 //!
 //! The standard library assumes explicit indication of the port number when creating a stream or
@@ -83,6 +140,18 @@
 //!
 //! The `.with_default_port(...)` function will check if the port number is specified and add it if
 //! necessary.
+
+pub mod normalize;
+mod parser;
+pub mod reference;
+mod resolve;
+#[cfg(any(test, feature = "test_util"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test_util")))]
+pub mod test_util;
+
+pub use parser::{parse_socket_addr_with_default, scheme_default_port, strip_scheme, AddrParser, AmbiguousIpv6, AnnotatedEndpoint, Cluster, DisplayHostForUri, EndpointDiff, HostAndPort, ListError, NumericInput, OutOfRangePort, ParseError, PortOffsetError, SpannedError};
+pub use resolve::*;
+
 maybe_async_cfg::content! {
 
 #![maybe_async_cfg::default(
@@ -90,12 +159,15 @@ maybe_async_cfg::content! {
         async_std(sync="std", async, tokio="tokio"),
         ToSocketAddrs(use, sync, async="ToSocketAddrsAsync", tokio="ToSocketAddrsTokio"),
         ToSocketAddrsWithDefaultPort(sync, async="ToSocketAddrsWithDefaultPortAsync", tokio="ToSocketAddrsWithDefaultPortTokio"),
-        into_vec4(fn, tokio="into_vec4_tokio"),
-        into_vec6(fn, tokio="into_vec6_tokio"),
+        apply_default_port(fn, sync, tokio="apply_default_port_tokio"),
+        into_vec4(fn, sync, tokio="into_vec4_tokio"),
+        into_vec6(fn, sync, tokio="into_vec6_tokio"),
     )
 )]
 
-use std::net::{SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr, Ipv6Addr};
+use std::{fmt, io, net::Ipv6Addr};
+#[cfg(any(feature = "sync", feature = "async", feature = "tokio"))]
+use std::{num::NonZeroU16, net::{SocketAddr, SocketAddrV4, SocketAddrV6, IpAddr, Ipv4Addr}, ops::Deref, pin::Pin, rc::Rc, sync::Arc};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
@@ -114,6 +186,15 @@ use async_std::net::ToSocketAddrs;
 pub trait ToSocketAddrsWithDefaultPort {
     type Inner: Sized + ToSocketAddrs;
     fn with_default_port(&self, default_port: u16) -> Self::Inner;
+
+    /// Same as `with_default_port`, for protocols with a plaintext/TLS pair of default ports
+    /// (like 80/443 for HTTP): uses `tls_port` if `use_tls` is set and `plain_port` otherwise.
+    ///
+    /// Only the port is picked: the returned address has no room for the TLS flag. To keep it
+    /// with the endpoint, use [`AnnotatedEndpoint::with_port_pair`] instead.
+    fn with_default_port_pair(&self, plain_port: u16, tls_port: u16, use_tls: bool) -> Self::Inner {
+        self.with_default_port(if use_tls { tls_port } else { plain_port })
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -143,6 +224,42 @@ std_impl!((IpAddr, u16));
 std_impl!((Ipv4Addr, u16));
 std_impl!((Ipv6Addr, u16));
 
+// This types hold port inside as well, but have to be converted to std types first
+macro_rules! nonzero_impl {
+    ($ip:ty) => {
+        #[maybe_async_cfg::maybe(
+            keep_self, 
+            sync(key="sync", feature="sync"),
+            async(key="async", feature="async"), 
+            async(key="tokio", feature="tokio"), 
+        )]
+        impl ToSocketAddrsWithDefaultPort for ($ip, NonZeroU16) {
+            type Inner = ($ip, u16);
+            fn with_default_port(&self, _default_port: u16) -> Self::Inner {
+                (self.0, self.1.get())
+            }
+        }
+    }
+}
+
+nonzero_impl!(IpAddr);
+nonzero_impl!(Ipv4Addr);
+nonzero_impl!(Ipv6Addr);
+
+// Full IPv6 tuple form, as accepted by `SocketAddrV6::new(ip, port, flowinfo, scope_id)`
+#[maybe_async_cfg::maybe(
+    keep_self, 
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl ToSocketAddrsWithDefaultPort for (Ipv6Addr, u16, u32, u32) {
+    type Inner = SocketAddrV6;
+    fn with_default_port(&self, _default_port: u16) -> Self::Inner {
+        SocketAddrV6::new(self.0, self.1, self.2, self.3)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 // This types hold IP address only, so we always have to use default port
@@ -169,6 +286,74 @@ tuple_impl!(Ipv6Addr);
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// An IPv6 address with a scope ID, to be combined with a default port.
+///
+/// Unlike a bare `Ipv6Addr`, it keeps the scope ID, so link-local targets can be specified without
+/// going through strings. The constructors are `const`, so it can be used in constants and
+/// `static` tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ScopedIpv6 {
+    pub addr: Ipv6Addr,
+    pub scope_id: u32,
+    pub flowinfo: u32,
+}
+
+impl ScopedIpv6 {
+    pub const fn new(addr: Ipv6Addr, scope_id: u32) -> Self {
+        Self { addr, scope_id, flowinfo: 0 }
+    }
+
+    /// Creates a scoped address with the scope ID of the network interface `interface` (like
+    /// `eth0`), for link-local targets from configs that name the interface. A numeric name is
+    /// taken as the scope ID itself.
+    ///
+    /// Interface names are looked up on Linux only (in `/sys/class/net`); on other platforms they
+    /// fail with `ErrorKind::Unsupported`.
+    pub fn with_interface(addr: Ipv6Addr, interface: &str) -> io::Result<Self> {
+        if let Ok(scope_id) = interface.parse() {
+            return Ok(Self::new(addr, scope_id));
+        }
+        if interface.is_empty() || interface.contains(['/', '\0']) || interface.starts_with('.') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid interface name {:?}", interface)));
+        }
+        Ok(Self::new(addr, interface_index(interface)?))
+    }
+
+    /// Sets the IPv6 flow label of the resulting address.
+    pub const fn with_flowinfo(self, flowinfo: u32) -> Self {
+        Self { flowinfo, ..self }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn interface_index(interface: &str) -> io::Result<u32> {
+    let path = format!("/sys/class/net/{}/ifindex", interface);
+    std::fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_index(interface: &str) -> io::Result<u32> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, format!("cannot look up interface {:?} on this platform", interface)))
+}
+
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl ToSocketAddrsWithDefaultPort for ScopedIpv6 {
+    type Inner = SocketAddrV6;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        SocketAddrV6::new(self.addr, default_port, self.flowinfo, self.scope_id)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
 #[maybe_async_cfg::maybe(
     sync(key="sync", feature="sync"),
     async(key="async", feature="async"), 
@@ -181,6 +366,18 @@ impl<'s> ToSocketAddrsWithDefaultPort for &'s [SocketAddr] {
     }
 }
 
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl<'s> ToSocketAddrsWithDefaultPort for &'s ResolvedAddrs {
+    type Inner = &'s [SocketAddr];
+    fn with_default_port(&self, _default_port: u16) -> Self::Inner {
+        self.as_slice()
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 #[maybe_async_cfg::maybe(
@@ -195,8 +392,101 @@ impl<T: ToSocketAddrs + ?Sized> ToSocketAddrsWithDefaultPort for &T where T: ToS
     }
 }
 
+// Smart pointers forward to the pointee, like references do
+macro_rules! ptr_impl {
+    ($ptr:ident) => {
+        #[maybe_async_cfg::maybe(
+            keep_self, 
+            sync(key="sync", feature="sync"),
+            async(key="async", feature="async"), 
+            async(key="tokio", feature="tokio"), 
+        )]
+        impl<T: ToSocketAddrsWithDefaultPort + ?Sized> ToSocketAddrsWithDefaultPort for $ptr<T> {
+            type Inner = <T as ToSocketAddrsWithDefaultPort>::Inner;
+            fn with_default_port(&self, default_port: u16) -> Self::Inner {
+                (**self).with_default_port( default_port )
+            }
+        }
+    };
+}
+
+ptr_impl!(Box);
+ptr_impl!(Rc);
+ptr_impl!(Arc);
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl<T: ToSocketAddrsWithDefaultPort + ?Sized> ToSocketAddrsWithDefaultPort for &mut T {
+    type Inner = <T as ToSocketAddrsWithDefaultPort>::Inner;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        (**self).with_default_port( default_port )
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl<P: Deref> ToSocketAddrsWithDefaultPort for Pin<P> where P::Target: ToSocketAddrsWithDefaultPort {
+    type Inner = <P::Target as ToSocketAddrsWithDefaultPort>::Inner;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        (**self).with_default_port( default_port )
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Writes `addr` into `out` with `default_port` applied if needed, producing exactly the string
+/// that `addr.with_default_port(default_port)` returns, but into a caller-provided buffer (for
+/// example, a `String` reused between calls).
+///
+/// ```rust
+/// let mut buffer = String::new();
+/// for addr in ["example.com", "[::1]:8080"] {
+///     buffer.clear();
+///     to_socket_addrs::write_with_default_port(addr, 80, &mut buffer).unwrap();
+///     println!("{}", buffer);
+/// }
+/// ```
+pub fn write_with_default_port<W: fmt::Write + ?Sized>(addr: &str, default_port: u16, out: &mut W) -> fmt::Result {
+    if let Some(pcolon) = addr.rfind(':') {
+        if let Some(pbracket) = addr.rfind(']') {
+            if pbracket < pcolon {
+                if pcolon + 1 == addr.len() {
+                    // "__]__:" => IPv6 in brackets with empty port
+                    write!(out, "{}{}", addr, default_port)
+                } else {
+                    // "__]__:__" => IPv6 in brackets with port
+                    out.write_str(addr)
+                }
+            } else {
+                // "__:__]__" => IPv6 in brackets without port
+                write!(out, "{}:{}", addr, default_port)
+            }
+        } else {
+            // "__:__", no brackets => IPv4 with port or bare IPv6
+            if addr[..pcolon].contains(':') {
+                // "__:__:__", no brackets => bare IPv6
+                write!(out, "[{}]:{}", addr, default_port)
+            } else if pcolon + 1 == addr.len() {
+                // "__:", no brackets, no more colons => IPv4 with empty port
+                write!(out, "{}{}", addr, default_port)
+            } else {
+                // "__:__", no brackets, no more colons => IPv4 with port
+                out.write_str(addr)
+            }
+        }
+    } else {
+        // "__", no colons => IPv4 without port
+        write!(out, "{}:{}", addr, default_port)
+    }
+}
+
 macro_rules! str_impl {
     ($ty:ty) => {
         #[maybe_async_cfg::maybe(
@@ -209,29 +499,9 @@ macro_rules! str_impl {
             type Inner = String;
 
             fn with_default_port(&self, default_port: u16) -> Self::Inner {
-                let inner = if let Some(pcolon) = self.rfind(":") {
-                    if let Some(pbracket) = self.rfind("]") {
-                        if pbracket < pcolon {
-                            // "__]__:__" => IPv6 in brackets with port
-                            self.to_string()
-                        } else {
-                            // "__:__]__" => IPv6 in brackets without port
-                            format!("{}:{}", self, default_port)
-                        }
-                    } else {
-                        // "__:__", no brackets => IPv4 with port or bare IPv6
-                        if let Some(_) = self[..pcolon].rfind(":") {
-                            // "__:__:__", no brackets => bare IPv6
-                            format!("[{}]:{}", self, default_port)
-                        } else {
-                            // "__:__", no brackets, no more colons => IPv4 with port
-                            self.to_string()
-                        }
-                    }
-                } else {
-                    // "__", no colons => IPv4 without port
-                    format!("{}:{}", self, default_port)
-                };
+                // Room for the brackets and the longest port
+                let mut inner = String::with_capacity(self.len() + 8);
+                write_with_default_port(self, default_port, &mut inner).expect("writing into a String cannot fail");
                 inner
             }
         }
@@ -243,111 +513,139 @@ str_impl!(String);
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use pretty_assertions::assert_eq;
+/// An adapter for values that display as an address (`host` or `host:port`), like types from other
+/// crates: the value is rendered once and then handled the same way as a string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DisplayAddr<T: fmt::Display>(pub T);
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="tokio", feature="tokio"), 
-        async(key="async", feature="async"), 
-    )]
-    #[maybe_async_cfg::only_if(sync)]
-    fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = addr
-            .with_default_port(default_port)
-            .to_socket_addrs()
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl<T: fmt::Display> ToSocketAddrsWithDefaultPort for DisplayAddr<T> {
+    type Inner = String;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        ToSocketAddrsWithDefaultPort::with_default_port(self.0.to_string().as_str(), default_port)
     }
+}
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="tokio", feature="tokio"), 
-        async(key="async", feature="async"), 
-    )]
-    #[maybe_async_cfg::only_if(sync)]
-    fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = addr
-            .with_default_port(default_port)
-            .to_socket_addrs()
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// An adapter for values that already implement `ToSocketAddrs` of the runtime and always carry
+/// a port, like third-party types this crate has no impls for: the default port is ignored and
+/// a clone of the value is passed through.
+///
+/// Useful while migrating to this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AssumePortPresent<T>(pub T);
+
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl<T: ToSocketAddrs + Clone> ToSocketAddrsWithDefaultPort for AssumePortPresent<T> {
+    type Inner = T;
+    fn with_default_port(&self, _default_port: u16) -> Self::Inner {
+        self.0.clone()
     }
+}
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="async", feature="async"), 
-        async(key="tokio", feature="tokio"), 
-    )]
-    #[maybe_async_cfg::only_if(async)]
-    async fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = addr
-            .with_default_port(default_port)
-            .to_socket_addrs()
-            .await
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// The string form has no room for the IPv6 flow label: `HostAndPort::try_to_socket_addr` keeps it
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl ToSocketAddrsWithDefaultPort for HostAndPort {
+    type Inner = String;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        HostAndPort::new(self.host(), Some(self.port_or(default_port))).to_string()
     }
+}
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="async", feature="async"), 
-        async(key="tokio", feature="tokio"), 
-    )]
-    #[maybe_async_cfg::only_if(async)]
-    async fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = addr
-            .with_default_port(default_port)
-            .to_socket_addrs()
-            .await
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+#[maybe_async_cfg::maybe(
+    keep_self,
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+impl ToSocketAddrsWithDefaultPort for AnnotatedEndpoint {
+    type Inner = String;
+    fn with_default_port(&self, default_port: u16) -> Self::Inner {
+        ToSocketAddrsWithDefaultPort::with_default_port(self.endpoint(), default_port)
     }
+}
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="async", feature="async"), 
-        async(key="tokio", feature="tokio"), 
-    )]
-    #[maybe_async_cfg::only_if(tokio)]
-    async fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = tokio::net::lookup_host(addr.with_default_port(default_port))
-            .await
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Applies `default_port` to every input, keeping each original input next to its normalized
+/// form (for example, to report errors against the original config entries).
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))), 
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))), 
+)]
+pub fn apply_default_port<A, I>(inputs: I, default_port: u16) -> Vec<(A, A::Inner)>
+where
+    A: ToSocketAddrsWithDefaultPort,
+    I: IntoIterator<Item = A>,
+{
+    inputs
+        .into_iter()
+        .map(|input| {
+            let inner = input.with_default_port(default_port);
+            (input, inner)
+        })
+        .collect()
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+#[cfg(any(feature = "sync", feature = "async", feature = "tokio"))]
+mod test {
+    use super::*;
+    use crate::test_util::*;
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn exact_size() {
+        // Pass-through impls keep the iterators of std, which know their size and can be reversed
+        fn reversed<I: ExactSizeIterator<Item = SocketAddr> + DoubleEndedIterator>(iter: I) -> Vec<SocketAddr> {
+            assert_eq!(iter.len(), iter.size_hint().0);
+            iter.rev().collect()
+        }
+
+        let list = [SocketAddr::from(([10, 0, 0, 1], 53)), SocketAddr::from(([10, 0, 0, 2], 53))];
+        let single = ToSocketAddrsWithDefaultPort::with_default_port(&list[0], 80);
+        let slice = ToSocketAddrsWithDefaultPort::with_default_port(&&list[..], 80);
+        assert_eq!(reversed(std::net::ToSocketAddrs::to_socket_addrs(&single).unwrap()), [list[0]]);
+        assert_eq!(reversed(std::net::ToSocketAddrs::to_socket_addrs(&slice).unwrap()),  [list[1], list[0]]);
     }
 
-    #[maybe_async_cfg::maybe(
-        sync(key="sync", feature="sync"),
-        async(key="async", feature="async"), 
-        async(key="tokio", feature="tokio"), 
-    )]
-    #[maybe_async_cfg::only_if(tokio)]
-    async fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
-        let mut v: Vec<String> = tokio::net::lookup_host(addr.with_default_port(default_port))
-            .await
-            .unwrap()
-            .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
-            .collect();
-        v.sort();
-        v
+    #[cfg(feature = "sync")]
+    #[test]
+    fn pathological() {
+        let long = "a".repeat(1_000_000);
+        let brackets = "[".repeat(100_000);
+        let colons = ":".repeat(100_000);
+        let inputs = [
+            "", ":", "::", "[", "]", "[]", "[]:", "[:", "]:", "][", "[[::1]]", "[::1]]:80", "a\0b:80", "\0",
+            "é:", "[é]:ü", "ä:ö:ü", "：８０", &long, &brackets, &colons,
+        ];
+
+        // Must not panic, whatever the input is
+        for input in inputs {
+            let _ = ToSocketAddrsWithDefaultPort::with_default_port(input, 80);
+        }
     }
 
     ////////////////////////////////////////////////////////////////////////////////////////////////
@@ -362,6 +660,13 @@ mod test {
         assert_eq!(into_vec4("8.8.8.8", 443).await,            ["8.8.8.8:443"]);
         // IPv4 with port
         assert_eq!(into_vec4("8.8.8.8:8080", 443).await,       ["8.8.8.8:8080"]);
+        // IPv4 with non-zero port
+        assert_eq!(into_vec4((Ipv4Addr::new(8, 8, 8, 8), NonZeroU16::new(53).unwrap()), 443).await,  ["8.8.8.8:53"]);
+        // IPv4 with empty port
+        assert_eq!(into_vec4("8.8.8.8:", 443).await,           ["8.8.8.8:443"]);
+        // Plaintext/TLS port pair
+        assert_eq!(ToSocketAddrsWithDefaultPort::with_default_port_pair("8.8.8.8", 80, 443, true),        "8.8.8.8:443");
+        assert_eq!(ToSocketAddrsWithDefaultPort::with_default_port_pair("8.8.8.8:8080", 80, 443, false),  "8.8.8.8:8080");
     }
 
     #[maybe_async_cfg::maybe(
@@ -374,6 +679,112 @@ mod test {
         assert_eq!(into_vec6("::1", 80).await,                 ["[::1]:80"]);
         assert_eq!(into_vec6("[::1]", 80).await,               ["[::1]:80"]);
         assert_eq!(into_vec6("[::1]:31337", 80).await,         ["[::1]:31337"]);
+        // IPv6 with empty port
+        assert_eq!(into_vec6("[::1]:", 80).await,              ["[::1]:80"]);
+        // Parsed host and port
+        assert_eq!(into_vec6("[::1]".parse::<HostAndPort>().unwrap(), 80).await, ["[::1]:80"]);
+        assert_eq!(into_vec6(AddrParser::new().parse_annotated("[::1]#zone=a").unwrap(), 80).await, ["[::1]:80"]);
+        // Displayable values
+        assert_eq!(into_vec6(DisplayAddr(Ipv6Addr::LOCALHOST), 80).await,  ["[::1]:80"]);
+        // Values with a port passed through as is
+        assert_eq!(into_vec6(AssumePortPresent("[::1]:8080"), 80).await,   ["[::1]:8080"]);
+        assert_eq!(into_vec6(AssumePortPresent((Ipv6Addr::LOCALHOST, 8443)), 80).await, ["[::1]:8443"]);
+        assert_eq!(into_vec6(DisplayAddr(format_args!("[::{}]:{}", 1, 443)), 80).await,  ["[::1]:443"]);
+        // Full IPv6 tuple
+        assert_eq!(into_vec6((Ipv6Addr::LOCALHOST, 8080, 0, 0), 80).await,                       ["[::1]:8080"]);
+        assert_eq!(into_vec6((Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 8080, 0, 3), 80).await, ["[fe80::1%3]:8080"]);
+        // Scoped IPv6 strings (the literals are parsed by std, so this does not depend on the platform)
+        assert_eq!(into_vec6("fe80::1%2", 80).await,           ["[fe80::1%2]:80"]);
+        assert_eq!(into_vec6("[fe80::1%2]", 80).await,         ["[fe80::1%2]:80"]);
+        assert_eq!(into_vec6("[fe80::1%2]:8080", 80).await,    ["[fe80::1%2]:8080"]);
+        // Scoped IPv6
+        assert_eq!(into_vec6(ScopedIpv6::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 2), 80).await, ["[fe80::1%2]:80"]);
+        assert_eq!(apply_default_port([ScopedIpv6::new(Ipv6Addr::LOCALHOST, 0).with_flowinfo(7)], 80)[0].1.flowinfo(), 7);
+        const ROUTER: ScopedIpv6 = ScopedIpv6::new(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1), 3).with_flowinfo(1);
+        assert_eq!(into_vec6(ROUTER, 80).await, ["[fe80::1%3]:80"]);
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test), 
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="pointers_tokio", tokio::test)
+    )]
+    async fn pointers() {
+        assert_eq!(into_vec4(Box::new("127.0.0.1"), 80).await,                      ["127.0.0.1:80"]);
+        assert_eq!(into_vec4(Rc::new(String::from("127.0.0.1:8080")), 80).await,    ["127.0.0.1:8080"]);
+        assert_eq!(into_vec4(Arc::new(Ipv4Addr::LOCALHOST), 80).await,              ["127.0.0.1:80"]);
+        assert_eq!(into_vec6(Arc::<str>::from("::1"), 80).await,                    ["[::1]:80"]);
+
+        let boxed: Box<dyn ToSocketAddrsWithDefaultPort<Inner = String>> = Box::new(String::from("127.0.0.1"));
+        assert_eq!(into_vec4(boxed, 80).await,                                      ["127.0.0.1:80"]);
+
+        let mut owned = String::from("127.0.0.1:8080");
+        assert_eq!(into_vec4(&mut owned, 80).await,                                 ["127.0.0.1:8080"]);
+        assert_eq!(into_vec4(Pin::new(&owned), 80).await,                           ["127.0.0.1:8080"]);
+        assert_eq!(into_vec4(Box::pin(Ipv4Addr::LOCALHOST), 80).await,              ["127.0.0.1:80"]);
+    }
+
+    #[test]
+    fn interface_scope() {
+        let link_local = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        assert_eq!(ScopedIpv6::with_interface(link_local, "7").unwrap(),                     ScopedIpv6::new(link_local, 7));
+        assert_eq!(ScopedIpv6::with_interface(link_local, "../lo").unwrap_err().kind(),      io::ErrorKind::InvalidInput);
+        assert_eq!(ScopedIpv6::with_interface(link_local, "").unwrap_err().kind(),           io::ErrorKind::InvalidInput);
+        assert!(ScopedIpv6::with_interface(link_local, "no-such-interface0").is_err());
+        #[cfg(target_os = "linux")]
+        assert_eq!(ScopedIpv6::with_interface(link_local, "lo").unwrap(),                    ScopedIpv6::new(link_local, 1));
+    }
+
+    #[test]
+    fn write_into_buffer() {
+        let mut buffer = String::from("stale");
+        for (addr, expected) in [("example.com", "example.com:80"), ("example.com:", "example.com:80"), ("::1", "[::1]:80"), ("[::1]:443", "[::1]:443")] {
+            buffer.clear();
+            write_with_default_port(addr, 80, &mut buffer).unwrap();
+            assert_eq!(buffer, expected);
+        }
+    }
+
+    #[test]
+    fn parity() {
+        assert_inner_parity("example.com", 80);
+        assert_inner_parity("example.com:", 80);
+        assert_inner_parity("[::1]:443", 80);
+        assert_inner_parity(&String::from("10.0.0.1"), 80);
+        assert_inner_parity(&(Ipv4Addr::LOCALHOST, 8080), 80);
+        assert_inner_parity(&(IpAddr::V6(Ipv6Addr::LOCALHOST), NonZeroU16::new(8080).unwrap()), 80);
+        assert_inner_parity(&SocketAddr::from(([10, 0, 0, 1], 53)), 80);
+        assert_inner_parity(&ScopedIpv6::new(Ipv6Addr::LOCALHOST, 2).with_flowinfo(7), 80);
+        assert_inner_parity(&DisplayAddr(Ipv4Addr::LOCALHOST), 80);
+        assert_inner_parity(&AssumePortPresent(SocketAddr::from(([10, 0, 0, 1], 53))), 80);
+        assert_inner_parity(&"example.com:8080".parse::<HostAndPort>().unwrap(), 80);
+    }
+
+    #[test]
+    fn generated_endpoints() {
+        let parser = AddrParser::new();
+        let mut generator = EndpointGenerator::new(7);
+        for shape in Shape::ALL {
+            for _ in 0..200 {
+                let endpoint = generator.generate(shape);
+                let parsed = parser.parse(&endpoint).unwrap_or_else(|err| panic!("{:?} {:?}: {}", shape, endpoint, err));
+                assert_eq!(parsed.port().is_some(), shape.has_port(), "{:?} {:?}", shape, endpoint);
+                assert_inner_parity(endpoint.as_str(), 80);
+            }
+        }
+        assert_eq!(EndpointGenerator::new(1).take(50).collect::<Vec<_>>(), EndpointGenerator::new(1).take(50).collect::<Vec<_>>());
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test), 
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="batch_tokio", tokio::test)
+    )]
+    async fn batch() {
+        assert_eq!(
+            apply_default_port(["10.0.0.1", "10.0.0.2:8443", "::1"], 443),
+            [("10.0.0.1", "10.0.0.1:443".to_string()), ("10.0.0.2:8443", "10.0.0.2:8443".to_string()), ("::1", "[::1]:443".to_string())]
+        );
     }
 
     #[maybe_async_cfg::maybe(
@@ -0,0 +1,64 @@
+//! Ready-made preprocessing steps for [`AddrParser::normalizer`](crate::AddrParser::normalizer).
+//!
+//! Every step takes and returns a `Cow<str>`, and borrows from its input whenever it only needs
+//! to cut something off.
+
+use std::borrow::Cow;
+
+/// Removes leading and trailing whitespace (`" example.com:80\n"`).
+pub fn trim(input: Cow<'_, str>) -> Cow<'_, str> {
+    slice(input, str::trim)
+}
+
+/// Removes one pair of matching quotes around the input (`"'example.com:80'"`), as left by some
+/// configuration formats.
+pub fn unquote(input: Cow<'_, str>) -> Cow<'_, str> {
+    slice(input, |input| {
+        ['"', '\'']
+            .into_iter()
+            .find_map(|quote| input.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(input)
+    })
+}
+
+/// Removes a URI scheme (`"https://"`), see [`strip_scheme`](crate::strip_scheme).
+pub fn strip_scheme(input: Cow<'_, str>) -> Cow<'_, str> {
+    slice(input, |input| crate::strip_scheme(input).1)
+}
+
+/// Removes everything from the first `/`, so a URI without a scheme is reduced to its
+/// authority (`"example.com:80/health"`).
+pub fn strip_path(input: Cow<'_, str>) -> Cow<'_, str> {
+    slice(input, |input| input.split('/').next().unwrap_or(input))
+}
+
+fn slice<'a>(input: Cow<'a, str>, part: impl Fn(&str) -> &str) -> Cow<'a, str> {
+    match input {
+        Cow::Borrowed(input) => Cow::Borrowed(part(input)),
+        Cow::Owned(input) if part(&input).len() == input.len() => Cow::Owned(input),
+        Cow::Owned(input) => Cow::Owned(part(&input).to_string()),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn steps() {
+        assert_eq!(trim(" example.com:80\n".into()),                "example.com:80");
+        assert_eq!(unquote("'example.com'".into()),                 "example.com");
+        assert_eq!(unquote("\"example.com'".into()),                "\"example.com'");
+        assert_eq!(unquote("\"".into()),                            "\"");
+        assert_eq!(strip_scheme("https://example.com/x".into()),    "example.com/x");
+        assert_eq!(strip_path("example.com:80/health?x".into()),    "example.com:80");
+        assert_eq!(strip_path("[::1]:80".into()),                   "[::1]:80");
+
+        assert!(matches!(trim(Cow::Borrowed(" a ")),                Cow::Borrowed("a")));
+        assert!(matches!(trim(Cow::Owned("a".into())),              Cow::Owned(_)));
+        assert_eq!(trim(Cow::Owned(" a ".into())),                  "a");
+    }
+}
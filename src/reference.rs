@@ -0,0 +1,97 @@
+//! Straightforward reference implementations of the crate's string handling, for differential
+//! testing and fuzzing.
+//!
+//! They follow the documented rules step by step, without any attention to speed, so an
+//! optimized implementation can be checked against them on arbitrary input:
+//!
+//! ```rust
+//! use to_socket_addrs::reference::with_default_port_naive;
+//!
+//! for input in ["example.com", "[::1]:", "::1", "a]b", ""] {
+//!     let mut fast = String::new();
+//!     to_socket_addrs::write_with_default_port(input, 80, &mut fast).unwrap();
+//!     assert_eq!(fast, with_default_port_naive(input, 80));
+//! }
+//! ```
+
+/// Applies `default_port` to `addr` the same way `ToSocketAddrsWithDefaultPort` does for strings
+/// (and [`write_with_default_port`](crate::write_with_default_port)):
+///
+/// 1. If there is a `]`, only the text after the last one matters: without a colon there, the
+///    port is missing (`"[::1]"`); with a colon at the very end, the port is empty (`"[::1]:"`);
+///    otherwise the port is present (`"[::1]:80"`).
+/// 2. Otherwise, with no colons the port is missing (`"example.com"`), with two or more colons
+///    the input is a bare IPv6 address without a port (`"::1"`), and with exactly one colon the
+///    port is empty if the colon is at the very end (`"example.com:"`) and present otherwise.
+///
+/// A missing port is added after a colon, an empty port is filled in, and a bare IPv6 address
+/// is enclosed in square brackets first. Input with a port is returned unchanged.
+pub fn with_default_port_naive(addr: &str, default_port: u16) -> String {
+    enum Port {
+        Missing,
+        Empty,
+        Present,
+        BareIpv6,
+    }
+
+    let port = match addr.rsplit_once(']') {
+        Some((_, after_bracket)) if !after_bracket.contains(':') => Port::Missing,
+        Some(_) if addr.ends_with(':') => Port::Empty,
+        Some(_) => Port::Present,
+        None => match addr.chars().filter(|&ch| ch == ':').count() {
+            0 => Port::Missing,
+            1 if addr.ends_with(':') => Port::Empty,
+            1 => Port::Present,
+            _ => Port::BareIpv6,
+        },
+    };
+
+    match port {
+        Port::Missing => format!("{}:{}", addr, default_port),
+        Port::Empty => format!("{}{}", addr, default_port),
+        Port::Present => addr.to_string(),
+        Port::BareIpv6 => format!("[{}]:{}", addr, default_port),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::EndpointGenerator;
+    use pretty_assertions::assert_eq;
+
+    fn assert_matches(input: &str, default_port: u16) {
+        let mut fast = String::new();
+        crate::write_with_default_port(input, default_port, &mut fast).unwrap();
+        assert_eq!(fast, with_default_port_naive(input, default_port), "input {:?}", input);
+    }
+
+    #[test]
+    fn exhaustive() {
+        // Every string of up to 6 characters over the alphabet that drives the heuristics
+        let alphabet = [':', '[', ']', 'a', '1'];
+        let mut inputs = vec![String::new()];
+        for _ in 0..6 {
+            inputs = inputs
+                .iter()
+                .flat_map(|input| alphabet.iter().map(move |ch| format!("{}{}", input, ch)))
+                .collect();
+            for input in &inputs {
+                assert_matches(input, 80);
+            }
+        }
+        assert_matches("", 0);
+    }
+
+    #[test]
+    fn generated() {
+        for endpoint in EndpointGenerator::new(11).take(5_000) {
+            assert_matches(&endpoint, 443);
+        }
+        for input in ["é:", "[é]:ü", "ä:ö:ü", "：８０", "a\0b:80"] {
+            assert_matches(input, 65535);
+        }
+    }
+}
@@ -0,0 +1,955 @@
+maybe_async_cfg::content! {
+
+#![maybe_async_cfg::default(
+    idents(
+        async_std(sync="std", async, tokio="tokio"),
+        ToSocketAddrs(use, sync, async="ToSocketAddrsAsync", tokio="ToSocketAddrsTokio"),
+        ToSocketAddrsWithDefaultPort(sync, async="ToSocketAddrsWithDefaultPortAsync", tokio="ToSocketAddrsWithDefaultPortTokio"),
+        lookup(fn, tokio="lookup_tokio"),
+        resolve_ordered(fn, sync, tokio="resolve_ordered_tokio"),
+        resolve_ordered_boxed(fn, tokio="resolve_ordered_boxed_tokio"),
+        resolve_validated(fn, sync, tokio="resolve_validated_tokio"),
+        resolve_all(fn, sync, tokio="resolve_all_tokio"),
+        resolve_first(fn, sync, tokio="resolve_first_tokio"),
+        resolve_with_intent(fn, sync, tokio="resolve_with_intent_tokio"),
+        resolve_with_family_ports(fn, sync, tokio="resolve_with_family_ports_tokio"),
+    )
+)]
+
+use std::{collections::HashSet, env, error, fmt, io, net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr}, sync::{Arc, OnceLock}};
+#[cfg(feature = "sync")]
+use std::{collections::HashMap, sync::{mpsc, Condvar, Mutex, PoisonError}, thread};
+#[cfg(any(feature = "async", feature = "tokio"))]
+use std::{future::Future, pin::Pin};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The order in which [`resolve_ordered`] returns addresses of different families.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Preference {
+    /// All IPv4 addresses first, then all IPv6 addresses.
+    Ipv4First,
+    /// All IPv6 addresses first, then all IPv4 addresses.
+    Ipv6First,
+    /// Alternate between families, starting with the family of the first resolved address
+    /// (as recommended by RFC 8305).
+    Interleaved,
+    /// IPv4 addresses only.
+    Ipv4Only,
+    /// IPv6 addresses only.
+    Ipv6Only,
+}
+
+impl Preference {
+    /// Returns the preference configured through environment variables, or `fallback` if none
+    /// is set:
+    ///
+    /// - `TSA_DISABLE_IPV6=1` selects [`Preference::Ipv4Only`],
+    /// - `TSA_PREFER_IPV4=1` selects [`Preference::Ipv4First`],
+    /// - `TSA_PREFER_IPV6=1` selects [`Preference::Ipv6First`].
+    ///
+    /// The variables are checked in this order. They are read on the first call only, later
+    /// calls reuse the cached result.
+    pub fn from_env(fallback: Preference) -> Preference {
+        static FROM_ENV: OnceLock<Option<Preference>> = OnceLock::new();
+        FROM_ENV
+            .get_or_init(|| Self::from_vars(|name| env::var(name).ok()))
+            .unwrap_or(fallback)
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Option<Preference> {
+        let flag = |name| matches!(var(name).as_deref(), Some("1" | "true" | "yes"));
+        if flag("TSA_DISABLE_IPV6") {
+            Some(Preference::Ipv4Only)
+        } else if flag("TSA_PREFER_IPV4") {
+            Some(Preference::Ipv4First)
+        } else if flag("TSA_PREFER_IPV6") {
+            Some(Preference::Ipv6First)
+        } else {
+            None
+        }
+    }
+
+    /// Removes duplicates from `addrs` and reorders them according to this preference.
+    ///
+    /// The relative order of addresses within each family is preserved.
+    pub fn apply(self, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        let mut seen = HashSet::new();
+        addrs.retain(|addr| seen.insert(*addr));
+
+        let (v4, v6): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.iter().partition(|addr| addr.is_ipv4());
+        match self {
+            Preference::Ipv4First => v4.into_iter().chain(v6).collect(),
+            Preference::Ipv6First => v6.into_iter().chain(v4).collect(),
+            Preference::Ipv4Only => v4,
+            Preference::Ipv6Only => v6,
+            Preference::Interleaved => {
+                let (first, second) = match addrs.first() {
+                    Some(SocketAddr::V6(_)) => (v6, v4),
+                    _ => (v4, v6),
+                };
+                let mut result = Vec::with_capacity(addrs.len());
+                let mut first = first.into_iter();
+                let mut second = second.into_iter();
+                loop {
+                    match (first.next(), second.next()) {
+                        (None, None) => break,
+                        (a, b) => result.extend(a.into_iter().chain(b)),
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Replaces IPv4-mapped IPv6 addresses (`[::ffff:10.0.0.1]:80`) with plain IPv4 ones
+/// (`10.0.0.1:80`), so equality checks and pool keys do not depend on whether the platform
+/// resolver returned mapped addresses. Other addresses, including their IPv6 scope IDs, are kept
+/// as they are.
+///
+/// Apply it before [`Preference::apply`] (which also removes the duplicates it may produce):
+///
+/// ```rust
+/// use to_socket_addrs::{canonicalize, resolve_ordered, Preference};
+///
+/// let addrs = Preference::Ipv4First.apply(canonicalize(resolve_ordered("[::ffff:10.0.0.1]", 80, Preference::Ipv4First)?));
+/// assert_eq!(addrs, [std::net::SocketAddr::from(([10, 0, 0, 1], 80))]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn canonicalize(mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    for addr in addrs.iter_mut() {
+        if let SocketAddr::V6(v6) = addr {
+            if let Some(v4) = v6.ip().to_ipv4_mapped() {
+                *addr = SocketAddr::from((v4, v6.port()));
+            }
+        }
+    }
+    addrs
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of resolved addresses that is cheap to clone and share between threads, for
+/// keeping lookup results in application state.
+///
+/// The addresses are stored as an `Arc<[SocketAddr]>` in the order they were given, and are
+/// never re-resolved: `ResolvedAddrs` implements `std::net::ToSocketAddrs`, and `&ResolvedAddrs`
+/// implements every variant of `ToSocketAddrsWithDefaultPort` (ignoring the default port, as the
+/// addresses already have ports).
+///
+/// ```rust
+/// use std::{net::ToSocketAddrs, thread};
+/// use to_socket_addrs::{resolve_ordered, Preference, ResolvedAddrs};
+///
+/// let addrs = ResolvedAddrs::from(resolve_ordered("127.0.0.1", 80, Preference::Ipv4First)?);
+/// let shared = addrs.clone();
+/// let worker = thread::spawn(move || shared.to_socket_addrs().map(|iter| iter.count()));
+/// assert_eq!(worker.join().unwrap()?, 1);
+/// assert_eq!(resolve_ordered(&addrs, 443, Preference::Ipv4First)?, addrs.as_slice());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ResolvedAddrs {
+    addrs: Arc<[SocketAddr]>,
+}
+
+impl ResolvedAddrs {
+    /// The addresses, in order.
+    pub fn as_slice(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    /// Copies the addresses into a `Vec`.
+    pub fn into_vec(self) -> Vec<SocketAddr> {
+        self.addrs.to_vec()
+    }
+
+    /// Returns the shared storage, without copying the addresses.
+    pub fn to_owned_arc(&self) -> Arc<[SocketAddr]> {
+        self.addrs.clone()
+    }
+
+    /// The number of addresses.
+    pub fn len(&self) -> usize {
+        self.addrs.len()
+    }
+
+    /// Whether there are no addresses.
+    pub fn is_empty(&self) -> bool {
+        self.addrs.is_empty()
+    }
+}
+
+impl From<Vec<SocketAddr>> for ResolvedAddrs {
+    fn from(addrs: Vec<SocketAddr>) -> Self {
+        Self { addrs: addrs.into() }
+    }
+}
+
+impl From<Arc<[SocketAddr]>> for ResolvedAddrs {
+    fn from(addrs: Arc<[SocketAddr]>) -> Self {
+        Self { addrs }
+    }
+}
+
+impl FromIterator<SocketAddr> for ResolvedAddrs {
+    fn from_iter<I: IntoIterator<Item = SocketAddr>>(iter: I) -> Self {
+        Self { addrs: iter.into_iter().collect() }
+    }
+}
+
+impl std::net::ToSocketAddrs for ResolvedAddrs {
+    type Iter = ResolvedAddrsIter;
+    fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
+        Ok(ResolvedAddrsIter { addrs: self.addrs.clone(), next: 0 })
+    }
+}
+
+/// An iterator over [`ResolvedAddrs`] sharing its storage, returned by `to_socket_addrs()`.
+#[derive(Clone, Debug)]
+pub struct ResolvedAddrsIter {
+    addrs: Arc<[SocketAddr]>,
+    next: usize,
+}
+
+impl Iterator for ResolvedAddrsIter {
+    type Item = SocketAddr;
+
+    fn next(&mut self) -> Option<SocketAddr> {
+        let addr = self.addrs.get(self.next).copied()?;
+        self.next += 1;
+        Some(addr)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.addrs.len() - self.next;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for ResolvedAddrsIter {}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// What resolved addresses are going to be used for, see [`resolve_with_intent`].
+///
+/// Follows `getaddrinfo` semantics for a missing host (the `AI_PASSIVE` flag).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Intent {
+    /// Connecting to a peer: a missing host means the local machine (loopback addresses).
+    Connect,
+    /// Binding a listener: a missing host means all interfaces (unspecified addresses).
+    Listen,
+}
+
+impl Intent {
+    /// The addresses `getaddrinfo` returns for a missing host with this intent.
+    pub fn default_addrs(self, port: u16) -> Vec<SocketAddr> {
+        let (v4, v6) = match self {
+            Intent::Connect => (Ipv4Addr::LOCALHOST, Ipv6Addr::LOCALHOST),
+            Intent::Listen => (Ipv4Addr::UNSPECIFIED, Ipv6Addr::UNSPECIFIED),
+        };
+        vec![SocketAddr::from((v6, port)), SocketAddr::from((v4, port))]
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The reason why a validation callback of [`resolve_validated`] rejected an address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// The address belongs to a private network.
+    Private,
+    /// The address is a loopback address.
+    Loopback,
+    /// The address is explicitly blocklisted by the application.
+    Blocklisted,
+    /// The address belongs to an unwanted family.
+    WrongFamily,
+    /// The address cannot be a valid connection target (see [`reject_bogons`]).
+    Bogon,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RejectReason::Private => "private",
+            RejectReason::Loopback => "loopback",
+            RejectReason::Blocklisted => "blocklisted",
+            RejectReason::WrongFamily => "wrong family",
+            RejectReason::Bogon => "bogon",
+        })
+    }
+}
+
+/// A validation callback for [`resolve_validated`] rejecting addresses that cannot be valid
+/// connection targets, as returned by some captive portals and broken resolvers:
+///
+/// - unspecified addresses and the IPv4 "this network" block (`0.0.0.0/8`, `::`),
+/// - the IPv4 broadcast address (`255.255.255.255`),
+/// - documentation prefixes (`192.0.2.0/24`, `198.51.100.0/24`, `203.0.113.0/24`,
+///   `2001:db8::/32`),
+/// - multicast addresses.
+///
+/// ```rust
+/// use to_socket_addrs::{reject_bogons, resolve_validated, Preference};
+///
+/// let addrs = resolve_validated("192.0.2.1", 80, Preference::Ipv4First, reject_bogons);
+/// assert_eq!(addrs.unwrap_err().kind(), std::io::ErrorKind::AddrNotAvailable);
+/// ```
+pub fn reject_bogons(addr: &SocketAddr) -> Result<(), RejectReason> {
+    let bogon = match addr.ip() {
+        IpAddr::V4(ip) => ip.octets()[0] == 0 || ip.is_broadcast() || ip.is_documentation() || ip.is_multicast(),
+        IpAddr::V6(ip) => ip.is_unspecified() || ip.segments()[..2] == [0x2001, 0xdb8] || ip.is_multicast(),
+    };
+    if bogon {
+        Err(RejectReason::Bogon)
+    } else {
+        Ok(())
+    }
+}
+
+/// The error returned by [`resolve_validated`] (wrapped into `io::Error` with
+/// `ErrorKind::AddrNotAvailable`) if every resolved address was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllRejected {
+    /// Rejected addresses with the reasons, in resolution order.
+    pub rejected: Vec<(SocketAddr, RejectReason)>,
+}
+
+impl fmt::Display for AllRejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "all resolved addresses were rejected")?;
+        for (i, (addr, reason)) in self.rejected.iter().enumerate() {
+            write!(f, "{} {} ({})", if i == 0 { ":" } else { "," }, addr, reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for AllRejected {}
+
+#[cfg(any(feature = "sync", feature = "async", feature = "tokio"))]
+fn apply_validation(addrs: Vec<SocketAddr>, mut validate: impl FnMut(&SocketAddr) -> Result<(), RejectReason>) -> io::Result<Vec<SocketAddr>> {
+    let mut accepted = Vec::with_capacity(addrs.len());
+    let mut rejected = Vec::new();
+    for addr in addrs {
+        match validate(&addr) {
+            Ok(()) => accepted.push(addr),
+            Err(reason) => rejected.push((addr, reason)),
+        }
+    }
+    if accepted.is_empty() && !rejected.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, AllRejected { rejected }));
+    }
+    Ok(accepted)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+)]
+use async_std::net::ToSocketAddrs;
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio"),
+)]
+use crate::ToSocketAddrsWithDefaultPort;
+
+#[cfg(any(feature = "sync", feature = "async", feature = "tokio"))]
+use crate::HostAndPort;
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio"),
+)]
+#[maybe_async_cfg::only_if(sync)]
+fn lookup<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok(addr.with_default_port(default_port).to_socket_addrs()?.collect())
+}
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio"),
+)]
+#[maybe_async_cfg::only_if(async)]
+async fn lookup<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok(addr.with_default_port(default_port).to_socket_addrs().await?.collect())
+}
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio"),
+)]
+#[maybe_async_cfg::only_if(tokio)]
+async fn lookup<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok(tokio::net::lookup_host(addr.with_default_port(default_port)).await?.collect())
+}
+
+/// Resolves `addr` (applying `default_port` if needed), removes duplicate addresses and orders
+/// the result according to `preference`.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_ordered<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16, preference: Preference) -> io::Result<Vec<SocketAddr>> {
+    Ok(preference.apply(lookup(addr, default_port).await?))
+}
+
+/// Resolves every address in `addrs` (applying `default_port` where needed) and combines the
+/// results, removing duplicates and ordering them according to `preference`.
+///
+/// Useful for seed or peer lists, for example those read by `AddrParser::parse_file(...)`.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_all<I>(addrs: I, default_port: u16, preference: Preference) -> io::Result<Vec<SocketAddr>>
+where
+    I: IntoIterator,
+    I::Item: ToSocketAddrsWithDefaultPort,
+{
+    let mut resolved = Vec::new();
+    for addr in addrs {
+        resolved.extend(lookup(addr, default_port).await?);
+    }
+    Ok(preference.apply(resolved))
+}
+
+/// Tries to resolve the candidates from `addrs` one by one (for example, primary and then
+/// replica) and returns the index of the first one that resolved to at least one address,
+/// together with its addresses ordered according to `preference`.
+///
+/// If no candidate resolves, returns the error of the last one (or `ErrorKind::NotFound` if the
+/// last one resolved to no addresses of the preferred families).
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_first<I>(addrs: I, default_port: u16, preference: Preference) -> io::Result<(usize, Vec<SocketAddr>)>
+where
+    I: IntoIterator,
+    I::Item: ToSocketAddrsWithDefaultPort,
+{
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no addresses to resolve");
+    for (index, addr) in addrs.into_iter().enumerate() {
+        match resolve_ordered(addr, default_port, preference).await {
+            Ok(resolved) if !resolved.is_empty() => return Ok((index, resolved)),
+            Ok(_) => last_error = io::Error::new(io::ErrorKind::NotFound, "no addresses resolved"),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+/// Same as [`resolve_ordered`], but passes every resolved address to `validate` and drops the
+/// rejected ones.
+///
+/// If addresses were resolved, but all of them were rejected, returns an error wrapping
+/// [`AllRejected`] with the list of rejected addresses and reasons.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_validated<A, F>(addr: A, default_port: u16, preference: Preference, validate: F) -> io::Result<Vec<SocketAddr>>
+where
+    A: ToSocketAddrsWithDefaultPort,
+    F: FnMut(&SocketAddr) -> Result<(), RejectReason>,
+{
+    apply_validation(resolve_ordered(addr, default_port, preference).await?, validate)
+}
+
+/// A resolution running on a background thread, started by [`resolve_in_background`].
+///
+/// Lets applications using the sync API (GUI or game loops, for example) poll for the result
+/// instead of blocking on the system resolver.
+#[cfg(feature = "sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+#[derive(Debug)]
+pub struct ResolveHandle {
+    receiver: mpsc::Receiver<io::Result<Vec<SocketAddr>>>,
+}
+
+#[cfg(feature = "sync")]
+impl ResolveHandle {
+    /// Returns the result if the resolution has finished, or `None` if it is still in progress.
+    ///
+    /// The result is returned only once; later calls return an error.
+    pub fn try_recv(&self) -> Option<io::Result<Vec<SocketAddr>>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(mpsc::TryRecvError::Empty) => None,
+            Err(mpsc::TryRecvError::Disconnected) => Some(Err(Self::gone())),
+        }
+    }
+
+    /// Blocks until the resolution has finished and returns its result.
+    pub fn wait(self) -> io::Result<Vec<SocketAddr>> {
+        self.receiver.recv().unwrap_or_else(|_| Err(Self::gone()))
+    }
+
+    fn gone() -> io::Error {
+        io::Error::other("the resolution result has already been taken or the resolver thread has failed")
+    }
+}
+
+/// Same as [`resolve_ordered`], but runs on a new background thread and returns immediately
+/// with a [`ResolveHandle`] to poll for the result.
+///
+/// Fails only if the thread cannot be spawned.
+#[cfg(feature = "sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+pub fn resolve_in_background<A>(addr: A, default_port: u16, preference: Preference) -> io::Result<ResolveHandle>
+where
+    A: ToSocketAddrsWithDefaultPort + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new()
+        .name("to-socket-addrs-resolver".into())
+        .spawn(move || {
+            // The handle may have been dropped already, nobody is interested in the result then
+            let _ = sender.send(resolve_ordered(addr, default_port, preference));
+        })?;
+    Ok(ResolveHandle { receiver })
+}
+
+#[cfg(feature = "sync")]
+type SharedResult = Result<Vec<SocketAddr>, Arc<io::Error>>;
+
+#[cfg(feature = "sync")]
+#[derive(Default)]
+struct Flight {
+    result: Mutex<Option<SharedResult>>,
+    done: Condvar,
+}
+
+/// Deduplicates concurrent resolutions of the same target ("singleflight"): while one thread
+/// is resolving it, other threads asking for the same target wait for that lookup and receive
+/// its result instead of issuing their own queries.
+///
+/// Results are not cached: a call made after the lookup has finished starts a new one.
+///
+/// Lookups are keyed by the `host:port` string built by `with_default_port`, so only targets
+/// whose `Inner` type is `String` are accepted: strings, [`HostAndPort`] and
+/// [`DisplayAddr`](crate::DisplayAddr) values (or pointers to them). Socket addresses need no
+/// lookup at all.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use to_socket_addrs::{Preference, SingleFlight};
+///
+/// let resolver = Arc::new(SingleFlight::new());
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let resolver = resolver.clone();
+///         std::thread::spawn(move || resolver.resolve("127.0.0.1", 443, Preference::Ipv4First))
+///     })
+///     .collect();
+/// for worker in workers {
+///     assert_eq!(worker.join().unwrap()?[0].to_string(), "127.0.0.1:443");
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[cfg(feature = "sync")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+#[derive(Default)]
+pub struct SingleFlight {
+    inflight: Mutex<HashMap<(String, Preference), Arc<Flight>>>,
+}
+
+#[cfg(feature = "sync")]
+impl fmt::Debug for SingleFlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inflight = self.inflight.lock().unwrap_or_else(PoisonError::into_inner);
+        f.debug_struct("SingleFlight").field("inflight", &inflight.len()).finish()
+    }
+}
+
+#[cfg(feature = "sync")]
+impl SingleFlight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same as [`resolve_ordered`], sharing the lookup with concurrent calls for the same target
+    /// (after applying `default_port`) and preference.
+    ///
+    /// Errors are shared as well: every caller receives an `io::Error` of the same kind, wrapping
+    /// an `Arc` of the original error, which keeps its message and source chain.
+    pub fn resolve<A>(&self, addr: A, default_port: u16, preference: Preference) -> io::Result<Vec<SocketAddr>>
+    where
+        A: ToSocketAddrsWithDefaultPort<Inner = String>,
+    {
+        let key = (addr.with_default_port(default_port), preference);
+        let (flight, leader) = {
+            let mut inflight = self.inflight.lock().unwrap_or_else(PoisonError::into_inner);
+            match inflight.get(&key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    inflight.insert(key.clone(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+
+        if leader {
+            let mut guard = FlightGuard { owner: self, key: &key, flight: &flight, result: None };
+            guard.result = Some(resolve_ordered(key.0.as_str(), default_port, preference).map_err(Arc::new));
+        }
+
+        let mut result = flight.result.lock().unwrap_or_else(PoisonError::into_inner);
+        while result.is_none() {
+            result = flight.done.wait(result).unwrap_or_else(PoisonError::into_inner);
+        }
+        match result.as_ref() {
+            Some(Ok(addrs)) => Ok(addrs.clone()),
+            Some(Err(err)) => Err(io::Error::new(err.kind(), err.clone())),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// Publishes the result of the leading lookup, even if it panics, so waiting threads never hang.
+#[cfg(feature = "sync")]
+struct FlightGuard<'a> {
+    owner: &'a SingleFlight,
+    key: &'a (String, Preference),
+    flight: &'a Flight,
+    result: Option<SharedResult>,
+}
+
+#[cfg(feature = "sync")]
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        self.owner.inflight.lock().unwrap_or_else(PoisonError::into_inner).remove(self.key);
+        let result = self.result.take().unwrap_or_else(|| Err(Arc::new(io::Error::other("resolution panicked"))));
+        *self.flight.result.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+        self.flight.done.notify_all();
+    }
+}
+
+/// Same as [`resolve_ordered`], but for a parsed target with an [`Intent`]: an empty host
+/// resolves to loopback addresses for [`Intent::Connect`] and to unspecified addresses for
+/// [`Intent::Listen`], without a DNS query. The `*` host means all interfaces, so it is only
+/// valid for [`Intent::Listen`]; with [`Intent::Connect`] it fails with
+/// [`io::ErrorKind::InvalidInput`]. Other hosts are resolved as usual.
+///
+/// ```rust
+/// use to_socket_addrs::{resolve_with_intent, HostAndPort, Intent, Preference};
+///
+/// let target: HostAndPort = "*:8080".parse()?;
+/// let addrs = resolve_with_intent(&target, 80, Preference::Ipv4First, Intent::Listen)?;
+/// assert_eq!(addrs[0].to_string(), "0.0.0.0:8080");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_with_intent(target: &HostAndPort, default_port: u16, preference: Preference, intent: Intent) -> io::Result<Vec<SocketAddr>> {
+    match (target.host(), intent) {
+        ("*", Intent::Connect) => Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot connect to the `*` host (all interfaces)")),
+        ("", _) | ("*", Intent::Listen) => Ok(preference.apply(intent.default_addrs(target.port_or(default_port)))),
+        _ => resolve_ordered(target.clone(), default_port, preference).await,
+    }
+}
+
+/// Same as [`resolve_ordered`], but for a parsed target without a port, applies `v4_port` to
+/// the resolved IPv4 addresses and `v6_port` to the IPv6 ones, for deployments exposing
+/// different ports on their IPv4 and IPv6 frontends. A port in the target wins over both.
+///
+/// ```rust
+/// use to_socket_addrs::{resolve_with_family_ports, HostAndPort, Preference};
+///
+/// let target: HostAndPort = "::1".parse()?;
+/// let addrs = resolve_with_family_ports(&target, 80, 8080, Preference::Ipv4First)?;
+/// assert_eq!(addrs[0].to_string(), "[::1]:8080");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync", inner(cfg_attr(docsrs, doc(cfg(feature = "sync"))))),
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+pub async fn resolve_with_family_ports(target: &HostAndPort, v4_port: u16, v6_port: u16, preference: Preference) -> io::Result<Vec<SocketAddr>> {
+    let mut addrs = resolve_ordered(target.clone(), v4_port, preference).await?;
+    if target.port().is_none() {
+        for addr in &mut addrs {
+            addr.set_port(if addr.is_ipv4() { v4_port } else { v6_port });
+        }
+    }
+    Ok(addrs)
+}
+
+/// A nameable future returned by `resolve_ordered_boxed_async(...)` and
+/// `resolve_ordered_boxed_tokio(...)`, suitable for storing in structs and trait objects.
+#[cfg(any(feature = "async", feature = "tokio"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "async", feature = "tokio"))))]
+pub type ResolveFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Vec<SocketAddr>>> + Send + 'a>>;
+
+/// Same as `resolve_ordered_async(...)`/`resolve_ordered_tokio(...)`, but returns a boxed
+/// [`ResolveFuture`] instead of an anonymous one.
+#[maybe_async_cfg::maybe(
+    async(key="async", feature="async", inner(cfg_attr(docsrs, doc(cfg(feature = "async"))))),
+    async(key="tokio", feature="tokio"),
+)]
+#[maybe_async_cfg::only_if(async)]
+pub fn resolve_ordered_boxed<'a, A>(addr: A, default_port: u16, preference: Preference) -> ResolveFuture<'a>
+where
+    A: ToSocketAddrsWithDefaultPort + Send + 'a,
+    A::Inner: Send,
+    <A::Inner as ToSocketAddrs>::Iter: Send,
+{
+    Box::pin(resolve_ordered(addr, default_port, preference))
+}
+
+/// Same as `resolve_ordered_async(...)`/`resolve_ordered_tokio(...)`, but returns a boxed
+/// [`ResolveFuture`] instead of an anonymous one.
+#[maybe_async_cfg::maybe(
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio", inner(cfg_attr(docsrs, doc(cfg(feature = "tokio"))))),
+)]
+#[maybe_async_cfg::only_if(tokio)]
+pub fn resolve_ordered_boxed<'a, A>(addr: A, default_port: u16, preference: Preference) -> ResolveFuture<'a>
+where
+    A: ToSocketAddrsWithDefaultPort + Send + 'a,
+    A::Inner: Send,
+{
+    Box::pin(resolve_ordered(addr, default_port, preference))
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+        list.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn preference() {
+        let mixed = addrs(&["[::1]:80", "10.0.0.1:80", "[::2]:80", "[::1]:80", "10.0.0.2:80", "10.0.0.3:80"]);
+
+        assert_eq!(Preference::Ipv4First.apply(mixed.clone()),      addrs(&["10.0.0.1:80", "10.0.0.2:80", "10.0.0.3:80", "[::1]:80", "[::2]:80"]));
+        assert_eq!(Preference::Ipv6First.apply(mixed.clone()),      addrs(&["[::1]:80", "[::2]:80", "10.0.0.1:80", "10.0.0.2:80", "10.0.0.3:80"]));
+        assert_eq!(Preference::Interleaved.apply(mixed.clone()),    addrs(&["[::1]:80", "10.0.0.1:80", "[::2]:80", "10.0.0.2:80", "10.0.0.3:80"]));
+        assert_eq!(Preference::Ipv4Only.apply(mixed.clone()),       addrs(&["10.0.0.1:80", "10.0.0.2:80", "10.0.0.3:80"]));
+        assert_eq!(Preference::Ipv6Only.apply(mixed),               addrs(&["[::1]:80", "[::2]:80"]));
+    }
+
+    #[test]
+    fn canonical() {
+        let mixed = addrs(&["[::ffff:10.0.0.1]:80", "10.0.0.1:80", "[::1]:80", "[fe80::1%2]:80", "[::ffff:10.0.0.2]:80"]);
+        assert_eq!(canonicalize(mixed.clone()),                        addrs(&["10.0.0.1:80", "10.0.0.1:80", "[::1]:80", "[fe80::1%2]:80", "10.0.0.2:80"]));
+        assert_eq!(Preference::Ipv6First.apply(canonicalize(mixed)),   addrs(&["[::1]:80", "[fe80::1%2]:80", "10.0.0.1:80", "10.0.0.2:80"]));
+    }
+
+    #[test]
+    fn bogons() {
+        let bogons = addrs(&["0.0.0.0:80", "0.1.2.3:80", "255.255.255.255:80", "192.0.2.1:80", "203.0.113.7:80", "224.0.0.1:80", "[::]:80", "[2001:db8::1]:80", "[ff02::1]:80"]);
+        for addr in &bogons {
+            assert_eq!(reject_bogons(addr), Err(RejectReason::Bogon), "{}", addr);
+        }
+        for addr in &addrs(&["10.0.0.1:80", "127.0.0.1:80", "8.8.8.8:53", "[::1]:80", "[2001:4860:4860::8888]:53"]) {
+            assert_eq!(reject_bogons(addr), Ok(()), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn preference_from_env() {
+        let vars = |set: &'static [(&'static str, &'static str)]| {
+            move |name: &str| set.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+        };
+
+        assert_eq!(Preference::from_vars(vars(&[])),                                                None);
+        assert_eq!(Preference::from_vars(vars(&[("TSA_PREFER_IPV4", "0")])),                       None);
+        assert_eq!(Preference::from_vars(vars(&[("TSA_PREFER_IPV4", "1")])),                       Some(Preference::Ipv4First));
+        assert_eq!(Preference::from_vars(vars(&[("TSA_PREFER_IPV6", "true")])),                    Some(Preference::Ipv6First));
+        assert_eq!(Preference::from_vars(vars(&[("TSA_PREFER_IPV6", "1"), ("TSA_DISABLE_IPV6", "1")])), Some(Preference::Ipv4Only));
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="ordered_tokio", tokio::test)
+    )]
+    async fn ordered() {
+        let input = addrs(&["10.0.0.1:53", "[::1]:53", "10.0.0.1:53"]);
+        assert_eq!(resolve_ordered(&input[..], 80, Preference::Ipv6First).await.unwrap(),  addrs(&["[::1]:53", "10.0.0.1:53"]));
+        assert_eq!(resolve_ordered("::1", 80, Preference::Ipv6Only).await.unwrap(),         addrs(&["[::1]:80"]));
+        assert_eq!(resolve_ordered("::1", 80, Preference::Ipv4Only).await.unwrap(),         addrs(&[]));
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="all_tokio", tokio::test)
+    )]
+    async fn all() {
+        let seeds: Vec<crate::HostAndPort> = ["10.0.0.1", "[::1]:8080", "10.0.0.1:80"].iter().map(|s| s.parse().unwrap()).collect();
+        assert_eq!(resolve_all(seeds, 80, Preference::Ipv4First).await.unwrap(), addrs(&["10.0.0.1:80", "[::1]:8080"]));
+        assert_eq!(resolve_all(Vec::<&str>::new(), 80, Preference::Ipv4First).await.unwrap(), addrs(&[]));
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="first_tokio", tokio::test)
+    )]
+    async fn first() {
+        assert_eq!(resolve_first(["::1", "10.0.0.1"], 80, Preference::Ipv4Only).await.unwrap(),   (1, addrs(&["10.0.0.1:80"])));
+        assert_eq!(resolve_first(["::1", "10.0.0.1"], 80, Preference::Ipv6Only).await.unwrap(),   (0, addrs(&["[::1]:80"])));
+        assert_eq!(resolve_first(["::1"], 80, Preference::Ipv4Only).await.unwrap_err().kind(),    io::ErrorKind::NotFound);
+        assert_eq!(resolve_first(Vec::<&str>::new(), 80, Preference::Ipv4Only).await.unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="validated_tokio", tokio::test)
+    )]
+    async fn validated() {
+        let input = addrs(&["127.0.0.1:53", "10.0.0.1:53", "[::1]:53"]);
+        let no_loopback = |addr: &SocketAddr| if addr.ip().is_loopback() { Err(RejectReason::Loopback) } else { Ok(()) };
+
+        assert_eq!(resolve_validated(&input[..], 80, Preference::Ipv4First, no_loopback).await.unwrap(), addrs(&["10.0.0.1:53"]));
+
+        let err = resolve_validated(&input[..2], 80, Preference::Ipv4First, |_: &SocketAddr| Err(RejectReason::Blocklisted)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AddrNotAvailable);
+        assert_eq!(err.to_string(), "all resolved addresses were rejected: 127.0.0.1:53 (blocklisted), 10.0.0.1:53 (blocklisted)");
+        let all_rejected = err.get_ref().and_then(|e| e.downcast_ref::<AllRejected>()).unwrap();
+        assert_eq!(all_rejected.rejected.len(), 2);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn single_flight() {
+        let resolver = Arc::new(SingleFlight::new());
+        let barrier = Arc::new(std::sync::Barrier::new(8));
+        let workers: Vec<_> = (0..8)
+            .map(|i| {
+                let (resolver, barrier) = (resolver.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    let target = if i % 2 == 0 { "127.0.0.1" } else { "[::1" };
+                    resolver.resolve(target, 80, Preference::Ipv4First)
+                })
+            })
+            .collect();
+        let direct = resolve_ordered("[::1", 80, Preference::Ipv4First).unwrap_err();
+        for (i, worker) in workers.into_iter().enumerate() {
+            match worker.join().unwrap() {
+                Ok(resolved) => assert_eq!((i % 2, resolved), (0, addrs(&["127.0.0.1:80"]))),
+                Err(err) => {
+                    assert_eq!(i % 2, 1);
+                    assert_eq!((err.kind(), err.to_string()), (direct.kind(), direct.to_string()));
+                    let shared = err.get_ref().and_then(|e| e.downcast_ref::<Arc<io::Error>>()).unwrap();
+                    assert_eq!(shared.kind(), direct.kind());
+                }
+            }
+        }
+        assert_eq!(format!("{:?}", resolver), "SingleFlight { inflight: 0 }");
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn in_background() {
+        let handle = resolve_in_background("127.0.0.1", 80, Preference::Ipv4First).unwrap();
+        let result = loop {
+            match handle.try_recv() {
+                Some(result) => break result,
+                None => thread::yield_now(),
+            }
+        };
+        assert_eq!(result.unwrap(), addrs(&["127.0.0.1:80"]));
+        assert!(handle.try_recv().unwrap().is_err());
+
+        let handle = resolve_in_background(String::from("::1"), 443, Preference::Ipv4First).unwrap();
+        assert_eq!(handle.wait().unwrap(), addrs(&["[::1]:443"]));
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="intent_tokio", tokio::test)
+    )]
+    async fn intent() {
+        let target = |input: &str| input.parse::<HostAndPort>().unwrap();
+        assert_eq!(resolve_with_intent(&target(":8080"), 80, Preference::Ipv4First, Intent::Listen).await.unwrap(),    addrs(&["0.0.0.0:8080", "[::]:8080"]));
+        assert_eq!(resolve_with_intent(&target("*"), 80, Preference::Ipv6Only, Intent::Listen).await.unwrap(),         addrs(&["[::]:80"]));
+        assert_eq!(resolve_with_intent(&target(""), 80, Preference::Ipv4First, Intent::Connect).await.unwrap(),        addrs(&["127.0.0.1:80", "[::1]:80"]));
+        assert_eq!(resolve_with_intent(&target("*:80"), 80, Preference::Ipv4First, Intent::Connect).await.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(resolve_with_intent(&target("10.0.0.1"), 80, Preference::Ipv4First, Intent::Listen).await.unwrap(), addrs(&["10.0.0.1:80"]));
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="resolved_addrs_tokio", tokio::test)
+    )]
+    async fn resolved_addrs() {
+        let resolved: ResolvedAddrs = resolve_all(["10.0.0.1", "::1"], 80, Preference::Ipv4First).await.unwrap().into();
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolve_ordered(&resolved, 443, Preference::Ipv6First).await.unwrap(), addrs(&["[::1]:80", "10.0.0.1:80"]));
+    }
+
+    #[test]
+    fn resolved_addrs_storage() {
+        let resolved: ResolvedAddrs = addrs(&["10.0.0.1:80", "[::1]:80"]).into_iter().collect();
+        let mut iter = std::net::ToSocketAddrs::to_socket_addrs(&resolved).unwrap();
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next(), Some(addrs(&["10.0.0.1:80"])[0]));
+        assert_eq!(iter.collect::<Vec<_>>(), addrs(&["[::1]:80"]));
+
+        let shared = resolved.to_owned_arc();
+        assert!(std::sync::Arc::ptr_eq(&shared, &resolved.clone().to_owned_arc()));
+        assert_eq!(ResolvedAddrs::from(shared), resolved);
+        assert_eq!(resolved.into_vec(), addrs(&["10.0.0.1:80", "[::1]:80"]));
+        assert!(ResolvedAddrs::default().is_empty());
+    }
+
+    #[maybe_async_cfg::maybe(
+        sync(key="sync", feature="sync", test),
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="family_ports_tokio", tokio::test)
+    )]
+    async fn family_ports() {
+        let target = |input: &str| input.parse::<HostAndPort>().unwrap();
+        assert_eq!(resolve_with_family_ports(&target("10.0.0.1"), 80, 8080, Preference::Ipv4First).await.unwrap(),   addrs(&["10.0.0.1:80"]));
+        assert_eq!(resolve_with_family_ports(&target("::1"), 80, 8080, Preference::Ipv4First).await.unwrap(),        addrs(&["[::1]:8080"]));
+        assert_eq!(resolve_with_family_ports(&target("[::1]:9000"), 80, 8080, Preference::Ipv4First).await.unwrap(), addrs(&["[::1]:9000"]));
+    }
+
+    #[maybe_async_cfg::maybe(
+        async(key="async", feature="async", async_attributes::test),
+        async(key="tokio", feature="tokio", self="ordered_boxed_tokio", tokio::test)
+    )]
+    async fn ordered_boxed() {
+        struct Pending {
+            future: ResolveFuture<'static>,
+        }
+
+        let pending = Pending { future: resolve_ordered_boxed("127.0.0.1", 80, Preference::Ipv4First) };
+        assert_eq!(pending.future.await.unwrap(), addrs(&["127.0.0.1:80"]));
+    }
+}
+
+}
@@ -0,0 +1,400 @@
+//! Helpers for writing concise assertions about address handling in tests.
+//!
+//! Enabled by the `test_util` feature.
+
+maybe_async_cfg::content! {
+
+#![maybe_async_cfg::default(
+    idents(
+        async_std(sync="std", async, tokio="tokio"),
+        ToSocketAddrs(use, sync, async="ToSocketAddrsAsync", tokio="ToSocketAddrsTokio"),
+        ToSocketAddrsWithDefaultPort(sync, async="ToSocketAddrsWithDefaultPortAsync", tokio="ToSocketAddrsWithDefaultPortTokio"),
+        into_vec4(fn, sync, tokio="into_vec4_tokio"),
+        into_vec6(fn, sync, tokio="into_vec6_tokio"),
+    )
+)]
+
+#[cfg(any(feature = "sync", feature = "async", feature = "tokio"))]
+use std::net::SocketAddr;
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+)]
+use async_std::net::ToSocketAddrs;
+
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"),
+    async(key="tokio", feature="tokio"),
+)]
+use crate::ToSocketAddrsWithDefaultPort;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv4 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="tokio", feature="tokio"), 
+    async(key="async", feature="async"), 
+)]
+#[maybe_async_cfg::only_if(sync)]
+pub fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = addr
+        .with_default_port(default_port)
+        .to_socket_addrs()
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv6 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="tokio", feature="tokio"), 
+    async(key="async", feature="async"), 
+)]
+#[maybe_async_cfg::only_if(sync)]
+pub fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = addr
+        .with_default_port(default_port)
+        .to_socket_addrs()
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv4 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+#[maybe_async_cfg::only_if(async)]
+pub async fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = addr
+        .with_default_port(default_port)
+        .to_socket_addrs()
+        .await
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv6 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+#[maybe_async_cfg::only_if(async)]
+pub async fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = addr
+        .with_default_port(default_port)
+        .to_socket_addrs()
+        .await
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv4 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+#[maybe_async_cfg::only_if(tokio)]
+pub async fn into_vec4<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = tokio::net::lookup_host(addr.with_default_port(default_port))
+        .await
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V4(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+/// Resolves `addr` (applying `default_port` if needed) and returns the IPv6 addresses as sorted
+/// strings.
+///
+/// Panics if resolution fails.
+#[maybe_async_cfg::maybe(
+    sync(key="sync", feature="sync"),
+    async(key="async", feature="async"), 
+    async(key="tokio", feature="tokio"), 
+)]
+#[maybe_async_cfg::only_if(tokio)]
+pub async fn into_vec6<A: ToSocketAddrsWithDefaultPort>(addr: A, default_port: u16) -> Vec<String> {
+    let mut v: Vec<String> = tokio::net::lookup_host(addr.with_default_port(default_port))
+        .await
+        .unwrap()
+        .filter_map(|a| if let SocketAddr::V6(_) = a {Some(a.to_string())} else {None})
+        .collect();
+    v.sort();
+    v
+}
+
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Produces the `Debug` form of the `Inner` value of `ToSocketAddrsWithDefaultPort`, or `None` if
+/// the `sync` feature is disabled. Used by [`assert_inner_parity`].
+pub trait SyncInner {
+    fn sync_inner(&self, default_port: u16) -> Option<String>;
+}
+
+#[cfg(feature = "sync")]
+impl<T: crate::ToSocketAddrsWithDefaultPort + ?Sized> SyncInner for T where T::Inner: std::fmt::Debug {
+    fn sync_inner(&self, default_port: u16) -> Option<String> {
+        Some(format!("{:?}", crate::ToSocketAddrsWithDefaultPort::with_default_port(self, default_port)))
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> SyncInner for T {
+    fn sync_inner(&self, _default_port: u16) -> Option<String> {
+        None
+    }
+}
+
+/// Produces the `Debug` form of the `Inner` value of `ToSocketAddrsWithDefaultPortAsync`, or
+/// `None` if the `async` feature is disabled. Used by [`assert_inner_parity`].
+pub trait AsyncInner {
+    fn async_inner(&self, default_port: u16) -> Option<String>;
+}
+
+#[cfg(feature = "async")]
+impl<T: crate::ToSocketAddrsWithDefaultPortAsync + ?Sized> AsyncInner for T where T::Inner: std::fmt::Debug {
+    fn async_inner(&self, default_port: u16) -> Option<String> {
+        Some(format!("{:?}", crate::ToSocketAddrsWithDefaultPortAsync::with_default_port(self, default_port)))
+    }
+}
+
+#[cfg(not(feature = "async"))]
+impl<T: ?Sized> AsyncInner for T {
+    fn async_inner(&self, _default_port: u16) -> Option<String> {
+        None
+    }
+}
+
+/// Produces the `Debug` form of the `Inner` value of `ToSocketAddrsWithDefaultPortTokio`, or
+/// `None` if the `tokio` feature is disabled. Used by [`assert_inner_parity`].
+pub trait TokioInner {
+    fn tokio_inner(&self, default_port: u16) -> Option<String>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T: crate::ToSocketAddrsWithDefaultPortTokio + ?Sized> TokioInner for T where T::Inner: std::fmt::Debug {
+    fn tokio_inner(&self, default_port: u16) -> Option<String> {
+        Some(format!("{:?}", crate::ToSocketAddrsWithDefaultPortTokio::with_default_port(self, default_port)))
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+impl<T: ?Sized> TokioInner for T {
+    fn tokio_inner(&self, _default_port: u16) -> Option<String> {
+        None
+    }
+}
+
+/// Checks that all enabled trait variants (sync, async, tokio) produce identical `Inner` values
+/// for `addr`, comparing their `Debug` forms.
+///
+/// No resolution is performed. Panics on mismatch, listing the value of every variant.
+pub fn assert_inner_parity<A: SyncInner + AsyncInner + TokioInner + ?Sized>(addr: &A, default_port: u16) {
+    let inners: Vec<(&str, String)> = [
+        ("sync", addr.sync_inner(default_port)),
+        ("async", addr.async_inner(default_port)),
+        ("tokio", addr.tokio_inner(default_port)),
+    ]
+    .into_iter()
+    .filter_map(|(variant, inner)| inner.map(|inner| (variant, inner)))
+    .collect();
+
+    if inners.windows(2).any(|pair| pair[0].1 != pair[1].1) {
+        panic!("`Inner` values differ between trait variants: {:?}", inners);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The shape of endpoints produced by [`EndpointGenerator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Shape {
+    /// `10.1.2.3`
+    Ipv4,
+    /// `10.1.2.3:8080`
+    Ipv4WithPort,
+    /// `fd00::1:2`
+    Ipv6,
+    /// `[fd00::1:2]`
+    Ipv6Bracketed,
+    /// `[fd00::1:2]:8080`
+    Ipv6WithPort,
+    /// `[fe80::1:2%eth0]:8080`
+    Ipv6WithZone,
+    /// `node-7.example.com`
+    Hostname,
+    /// `node-7.example.com:8080`
+    HostnameWithPort,
+}
+
+impl Shape {
+    /// All shapes, in declaration order.
+    pub const ALL: [Shape; 8] = [
+        Shape::Ipv4,
+        Shape::Ipv4WithPort,
+        Shape::Ipv6,
+        Shape::Ipv6Bracketed,
+        Shape::Ipv6WithPort,
+        Shape::Ipv6WithZone,
+        Shape::Hostname,
+        Shape::HostnameWithPort,
+    ];
+
+    /// Whether endpoints of this shape carry a port.
+    pub fn has_port(self) -> bool {
+        matches!(self, Shape::Ipv4WithPort | Shape::Ipv6WithPort | Shape::Ipv6WithZone | Shape::HostnameWithPort)
+    }
+}
+
+/// An infinite iterator of random endpoint strings, all of them accepted by `AddrParser`, for
+/// load-testing parsers and connection pools with realistic input.
+///
+/// The sequence is fully determined by the seed, so failures can be reproduced. Host names are
+/// generated, not real, and are not expected to resolve.
+///
+/// ```rust
+/// use to_socket_addrs::{test_util::{EndpointGenerator, Shape}, AddrParser};
+///
+/// let parser = AddrParser::new();
+/// for endpoint in EndpointGenerator::new(42).shapes(&[Shape::Ipv4WithPort, Shape::Ipv6]).take(100) {
+///     assert!(parser.parse(&endpoint).is_ok());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EndpointGenerator {
+    state: u64,
+    shapes: Vec<Shape>,
+}
+
+impl EndpointGenerator {
+    /// Creates a generator producing all shapes with equal probability.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed, shapes: Shape::ALL.to_vec() }
+    }
+
+    /// Restricts the generator to `shapes`, picked with equal probability (list a shape several
+    /// times to make it more frequent).
+    ///
+    /// Panics if `shapes` is empty.
+    pub fn shapes(mut self, shapes: &[Shape]) -> Self {
+        assert!(!shapes.is_empty(), "at least one shape is required");
+        self.shapes = shapes.to_vec();
+        self
+    }
+
+    /// Generates an endpoint of the given shape.
+    pub fn generate(&mut self, shape: Shape) -> String {
+        match shape {
+            Shape::Ipv4 => self.ipv4().to_string(),
+            Shape::Ipv4WithPort => format!("{}:{}", self.ipv4(), self.port()),
+            Shape::Ipv6 => self.ipv6().to_string(),
+            Shape::Ipv6Bracketed => format!("[{}]", self.ipv6()),
+            Shape::Ipv6WithPort => format!("[{}]:{}", self.ipv6(), self.port()),
+            Shape::Ipv6WithZone => {
+                let addr = std::net::Ipv6Addr::from(0xfe80u128 << 112 | u128::from(self.next_u64()));
+                let zone = match self.below(3) {
+                    0 => format!("eth{}", self.below(4)),
+                    1 => format!("en{}", self.below(4)),
+                    _ => (1 + self.below(16)).to_string(),
+                };
+                format!("[{}%{}]:{}", addr, zone, self.port())
+            }
+            Shape::Hostname => self.hostname(),
+            Shape::HostnameWithPort => format!("{}:{}", self.hostname(), self.port()),
+        }
+    }
+
+    fn ipv4(&mut self) -> std::net::Ipv4Addr {
+        std::net::Ipv4Addr::from(self.next_u64() as u32)
+    }
+
+    fn ipv6(&mut self) -> std::net::Ipv6Addr {
+        // Zero some groups to get compressed (`::`) forms too
+        let mask = (0..8).fold(0u128, |mask, group| if self.below(3) == 0 { mask } else { mask | 0xffff << (group * 16) });
+        std::net::Ipv6Addr::from((u128::from(self.next_u64()) << 64 | u128::from(self.next_u64())) & mask)
+    }
+
+    fn port(&mut self) -> u16 {
+        1 + self.below(u64::from(u16::MAX)) as u16
+    }
+
+    fn hostname(&mut self) -> String {
+        const TLDS: [&str; 5] = ["com", "net", "org", "internal", "local"];
+        const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-";
+
+        let mut labels: Vec<String> = (0..1 + self.below(3))
+            .map(|_| {
+                let len = 1 + self.below(12) as usize;
+                (0..len)
+                    .map(|i| {
+                        // Hyphens are not allowed at either end of a label
+                        let choices = if i == 0 || i == len - 1 { CHARS.len() - 1 } else { CHARS.len() };
+                        CHARS[self.below(choices as u64) as usize] as char
+                    })
+                    .collect()
+            })
+            .collect();
+        labels.push(TLDS[self.below(TLDS.len() as u64) as usize].to_string());
+        labels.join(".")
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// SplitMix64: tiny, fast and good enough for test input.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Iterator for EndpointGenerator {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let index = self.below(self.shapes.len() as u64) as usize;
+        let shape = self.shapes[index];
+        Some(self.generate(shape))
+    }
+}
@@ -0,0 +1,79 @@
+//! Checks that the sync, async and tokio variants coexist and agree when all runtime features
+//! are enabled at once (`cargo test --all-features`).
+
+#![cfg(all(feature = "sync", feature = "async", feature = "tokio"))]
+
+use std::net::SocketAddr;
+
+use to_socket_addrs::{
+    resolve_all, resolve_all_async, resolve_all_tokio, resolve_ordered_boxed_async, resolve_ordered_boxed_tokio,
+    resolve_with_family_ports, resolve_with_family_ports_async, resolve_with_family_ports_tokio, resolve_with_intent,
+    resolve_with_intent_async, resolve_with_intent_tokio, AddrParser, HostAndPort, Intent, Preference, ResolveFuture,
+    ToSocketAddrsWithDefaultPort, ToSocketAddrsWithDefaultPortAsync, ToSocketAddrsWithDefaultPortTokio,
+};
+
+const SEEDS: [&str; 3] = ["127.0.0.1", "[::1]:8080", "10.0.0.1:80"];
+
+fn addrs(list: &[&str]) -> Vec<SocketAddr> {
+    list.iter().map(|a| a.parse().unwrap()).collect()
+}
+
+fn listener() -> HostAndPort {
+    AddrParser::new().parse_listeners("http=:8080", &[]).unwrap().remove("http").unwrap()
+}
+
+#[test]
+fn traits() {
+    let sync = ToSocketAddrsWithDefaultPort::with_default_port("::1", 80);
+    let async_std = ToSocketAddrsWithDefaultPortAsync::with_default_port("::1", 80);
+    let tokio = ToSocketAddrsWithDefaultPortTokio::with_default_port("::1", 80);
+    assert_eq!(sync, "[::1]:80");
+    assert_eq!(async_std, sync);
+    assert_eq!(tokio, sync);
+}
+
+#[cfg(feature = "test_util")]
+#[test]
+fn parity() {
+    use std::net::{Ipv6Addr, SocketAddrV6};
+    use to_socket_addrs::{test_util::assert_inner_parity, AnnotatedEndpoint};
+
+    for seed in SEEDS {
+        assert_inner_parity(seed, 80);
+        assert_inner_parity(&seed.parse::<HostAndPort>().unwrap(), 80);
+        assert_inner_parity(&AnnotatedEndpoint::new(seed.parse().unwrap()).with("zone", "a"), 80);
+    }
+
+    // The flow label cannot be carried by any variant, so it must not make them differ
+    let labeled = HostAndPort::from(SocketAddrV6::new(Ipv6Addr::LOCALHOST, 443, 0x12345, 0));
+    assert_inner_parity(&labeled, 80);
+    assert_inner_parity(&AnnotatedEndpoint::new(labeled).with_server_name("example.com"), 80);
+    assert_inner_parity(&AddrParser::new().parse_annotated("example.com#zone=a").unwrap(), 80);
+}
+
+#[test]
+fn sync() {
+    assert_eq!(resolve_all(SEEDS, 6379, Preference::Ipv4First).unwrap(), addrs(&["127.0.0.1:6379", "10.0.0.1:80", "[::1]:8080"]));
+    assert_eq!(resolve_with_intent(&listener(), 80, Preference::Ipv4First, Intent::Listen).unwrap(), addrs(&["0.0.0.0:8080", "[::]:8080"]));
+    assert_eq!(resolve_with_family_ports(&"::1".parse().unwrap(), 80, 8080, Preference::Ipv4First).unwrap(), addrs(&["[::1]:8080"]));
+}
+
+#[async_attributes::test]
+async fn async_std() {
+    assert_eq!(resolve_all_async(SEEDS, 6379, Preference::Ipv4First).await.unwrap(), addrs(&["127.0.0.1:6379", "10.0.0.1:80", "[::1]:8080"]));
+    assert_eq!(resolve_with_intent_async(&listener(), 80, Preference::Ipv4First, Intent::Listen).await.unwrap(), addrs(&["0.0.0.0:8080", "[::]:8080"]));
+    assert_eq!(resolve_with_family_ports_async(&"::1".parse().unwrap(), 80, 8080, Preference::Ipv4First).await.unwrap(), addrs(&["[::1]:8080"]));
+
+    let future: ResolveFuture<'static> = resolve_ordered_boxed_async("127.0.0.1", 80, Preference::Ipv4First);
+    assert_eq!(future.await.unwrap(), addrs(&["127.0.0.1:80"]));
+}
+
+#[tokio::test]
+async fn tokio() {
+    assert_eq!(resolve_all_tokio(SEEDS, 6379, Preference::Ipv4First).await.unwrap(), addrs(&["127.0.0.1:6379", "10.0.0.1:80", "[::1]:8080"]));
+    assert_eq!(resolve_with_intent_tokio(&listener(), 80, Preference::Ipv4First, Intent::Listen).await.unwrap(), addrs(&["0.0.0.0:8080", "[::]:8080"]));
+    assert_eq!(resolve_with_family_ports_tokio(&"::1".parse().unwrap(), 80, 8080, Preference::Ipv4First).await.unwrap(), addrs(&["[::1]:8080"]));
+
+    let future: ResolveFuture<'static> = resolve_ordered_boxed_tokio("127.0.0.1", 80, Preference::Ipv4First);
+    assert_eq!(future.await.unwrap(), addrs(&["127.0.0.1:80"]));
+}